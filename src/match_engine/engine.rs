@@ -0,0 +1,289 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use rust_decimal::prelude::*;
+
+use crate::match_engine::orderbook::{Order, OrderBook, OrderSummary, SelfTradeBehavior};
+
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct TradingPair {
+    base: String,
+    quote: String,
+}
+
+impl TradingPair {
+    pub fn new(base: String, quote: String) -> TradingPair {
+        TradingPair { base, quote }
+    }
+}
+
+impl fmt::Display for TradingPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.base, self.quote)
+    }
+}
+
+/// Why a `place_limit_order`/`place_stop_order` call was rejected before it
+/// ever touched the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderError {
+    InvalidTick,
+    InvalidLotSize,
+    BelowMinimumSize,
+    MarketNotFound,
+    /// The order's `order_type`/`trigger_price` don't describe a valid stop
+    /// or stop-limit order.
+    InvalidStopOrder,
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::InvalidTick => write!(f, "price is not a multiple of the market's tick size"),
+            OrderError::InvalidLotSize => {
+                write!(f, "size is not a multiple of the market's lot size")
+            }
+            OrderError::BelowMinimumSize => {
+                write!(f, "size is below the market's minimum order size")
+            }
+            OrderError::MarketNotFound => {
+                write!(f, "the orderbook for the given trading pair does not exist")
+            }
+            OrderError::InvalidStopOrder => {
+                write!(f, "order is not a valid stop or stop-limit order")
+            }
+        }
+    }
+}
+
+impl Error for OrderError {}
+
+/// A single market: its order book plus the entry rules orders must satisfy
+/// before they're allowed to rest on it.
+struct Market {
+    orderbook: OrderBook,
+    tick_size: Decimal,
+    lot_size: f64,
+    min_size: f64,
+}
+
+impl Market {
+    fn new(tick_size: Decimal, lot_size: f64, min_size: f64) -> Market {
+        Market {
+            orderbook: OrderBook::new(),
+            tick_size,
+            lot_size,
+            min_size,
+        }
+    }
+
+    fn validate(&self, price: Decimal, size: f64) -> Result<(), OrderError> {
+        if price % self.tick_size != Decimal::ZERO {
+            return Err(OrderError::InvalidTick);
+        }
+
+        if !is_lot_size_multiple(size, self.lot_size) {
+            return Err(OrderError::InvalidLotSize);
+        }
+
+        if size < self.min_size {
+            return Err(OrderError::BelowMinimumSize);
+        }
+
+        Ok(())
+    }
+}
+
+// Unlike `price`/`tick_size`, which are `Decimal` and so compare exactly,
+// `size`/`lot_size` are `f64` and raw `%` accumulates rounding error — e.g.
+// `0.3 % 0.1` is `0.09999999999999998`, not `0.0`, which would wrongly
+// reject an exact 3-lot order. Treat the remainder as zero if it's within
+// an epsilon of either end of the lot-size interval.
+const LOT_SIZE_EPSILON: f64 = 1e-9;
+
+fn is_lot_size_multiple(size: f64, lot_size: f64) -> bool {
+    let remainder = size % lot_size;
+    remainder < LOT_SIZE_EPSILON || (lot_size - remainder) < LOT_SIZE_EPSILON
+}
+
+pub struct MatchingEngine {
+    markets: HashMap<TradingPair, Market>,
+}
+
+impl MatchingEngine {
+    pub fn new() -> MatchingEngine {
+        MatchingEngine {
+            markets: HashMap::new(),
+        }
+    }
+
+    pub fn add_new_market(
+        &mut self,
+        pair: TradingPair,
+        tick_size: Decimal,
+        lot_size: f64,
+        min_size: f64,
+    ) {
+        println!("opening new orderbook for market {:?}", pair);
+        self.markets
+            .insert(pair, Market::new(tick_size, lot_size, min_size));
+    }
+
+    pub fn place_limit_order(
+        &mut self,
+        pair: TradingPair,
+        price: Decimal,
+        order: Order,
+        stp: SelfTradeBehavior,
+    ) -> Result<OrderSummary, OrderError> {
+        match self.markets.get_mut(&pair) {
+            Some(market) => {
+                market.validate(price, order.size())?;
+                let summary = market.orderbook.place_limit_order(price, order, stp);
+                println!("placed limit order at price level {}", price);
+                Ok(summary)
+            }
+            None => Err(OrderError::MarketNotFound),
+        }
+    }
+
+    /// Arms a stop or stop-limit order on `pair`'s trigger book. Unlike
+    /// `place_limit_order`, it never matches immediately, so there's no
+    /// `OrderSummary` to return — the order only touches the live book once
+    /// its trigger fires. Validates against the order's own `trigger_price`
+    /// (there's no separate parameter to pass a different one and bypass
+    /// validation).
+    pub fn place_stop_order(&mut self, pair: TradingPair, order: Order) -> Result<(), OrderError> {
+        match self.markets.get_mut(&pair) {
+            Some(market) => {
+                let trigger_price = order.trigger_price().ok_or(OrderError::InvalidStopOrder)?;
+                market.validate(trigger_price, order.size())?;
+                market
+                    .orderbook
+                    .place_stop_order(order)
+                    .map_err(|_| OrderError::InvalidStopOrder)?;
+                println!("armed stop order at trigger price level {}", trigger_price);
+                Ok(())
+            }
+            None => Err(OrderError::MarketNotFound),
+        }
+    }
+
+    pub fn cancel_order(&mut self, pair: &TradingPair, order_id: u64) -> Result<Order, String> {
+        match self.markets.get_mut(pair) {
+            Some(market) => market
+                .orderbook
+                .cancel_order(order_id)
+                .ok_or_else(|| format!("order {} not found in market {:?}", order_id, pair)),
+            None => Err(format!(
+                "the orderbook for the given trading pair ({:?}) does not exist",
+                pair
+            )),
+        }
+    }
+
+    pub fn amend_order(
+        &mut self,
+        pair: &TradingPair,
+        order_id: u64,
+        new_size: f64,
+    ) -> Result<(), String> {
+        match self.markets.get_mut(pair) {
+            Some(market) => market.orderbook.amend_order(order_id, new_size),
+            None => Err(format!(
+                "the orderbook for the given trading pair ({:?}) does not exist",
+                pair
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::match_engine::orderbook::{BidOrAsk, OrderType, SelfTradeBehavior};
+
+    fn btc_usd() -> TradingPair {
+        TradingPair::new("BTC".to_string(), "USD".to_string())
+    }
+
+    #[test]
+    fn place_limit_order_rejects_unknown_market() {
+        let mut engine = MatchingEngine::new();
+        let order = Order::new(1, BidOrAsk::Bid, 1.0, 1);
+
+        let result = engine.place_limit_order(btc_usd(), dec!(100), order, SelfTradeBehavior::CancelProvide);
+        assert_eq!(result, Err(OrderError::MarketNotFound));
+    }
+
+    #[test]
+    fn place_limit_order_rejects_off_tick_price() {
+        let mut engine = MatchingEngine::new();
+        engine.add_new_market(btc_usd(), dec!(0.5), 1.0, 1.0);
+
+        let order = Order::new(1, BidOrAsk::Bid, 1.0, 1);
+        let result = engine.place_limit_order(btc_usd(), dec!(100.25), order, SelfTradeBehavior::CancelProvide);
+        assert_eq!(result, Err(OrderError::InvalidTick));
+    }
+
+    #[test]
+    fn place_limit_order_rejects_off_lot_size() {
+        let mut engine = MatchingEngine::new();
+        engine.add_new_market(btc_usd(), dec!(0.5), 0.5, 0.5);
+
+        let order = Order::new(1, BidOrAsk::Bid, 0.75, 1);
+        let result = engine.place_limit_order(btc_usd(), dec!(100), order, SelfTradeBehavior::CancelProvide);
+        assert_eq!(result, Err(OrderError::InvalidLotSize));
+    }
+
+    #[test]
+    fn place_limit_order_accepts_an_exact_lot_size_despite_f64_rounding() {
+        let mut engine = MatchingEngine::new();
+        engine.add_new_market(btc_usd(), dec!(0.5), 0.1, 0.1);
+
+        // 0.3 % 0.1 == 0.09999999999999998 in raw f64 arithmetic, even
+        // though 0.3 is an exact 3-lot order.
+        let order = Order::new(1, BidOrAsk::Bid, 0.3, 1);
+        let result = engine.place_limit_order(btc_usd(), dec!(100), order, SelfTradeBehavior::CancelProvide);
+        assert_eq!(
+            result,
+            Ok(OrderSummary {
+                posted_order_id: Some(1),
+                total_filled: 0.0,
+                remaining: 0.3,
+            })
+        );
+    }
+
+    #[test]
+    fn place_limit_order_rejects_below_minimum_size() {
+        let mut engine = MatchingEngine::new();
+        engine.add_new_market(btc_usd(), dec!(0.5), 0.5, 2.0);
+
+        let order = Order::new(1, BidOrAsk::Bid, 0.5, 1);
+        let result = engine.place_limit_order(btc_usd(), dec!(100), order, SelfTradeBehavior::CancelProvide);
+        assert_eq!(result, Err(OrderError::BelowMinimumSize));
+    }
+
+    #[test]
+    fn place_limit_order_accepts_valid_order() {
+        let mut engine = MatchingEngine::new();
+        engine.add_new_market(btc_usd(), dec!(0.5), 0.5, 0.5);
+
+        let order = Order::new(1, BidOrAsk::Bid, 1.5, 1);
+        let result = engine.place_limit_order(btc_usd(), dec!(100.5), order, SelfTradeBehavior::CancelProvide);
+        assert_eq!(
+            result,
+            Ok(OrderSummary {
+                posted_order_id: Some(1),
+                total_filled: 0.0,
+                remaining: 1.5,
+            })
+        );
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use rust_decimal::prelude::*;
-use std::collections::{HashMap, LinkedList};
+use std::collections::{BTreeMap, HashMap, LinkedList, VecDeque};
 
 #[derive(Debug, Clone)]
 pub enum BidOrAsk {
@@ -9,82 +9,505 @@ pub enum BidOrAsk {
     Ask,
 }
 
+/// How to resolve a match between two orders owned by the same account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTradeBehavior {
+    /// Cancel the smaller of the two orders outright and decrement the
+    /// larger by that amount, then keep matching.
+    DecrementAndCancel,
+    /// Cancel the resting maker order and skip it, then keep matching.
+    CancelProvide,
+    /// Abort the incoming taker order entirely.
+    CancelTake,
+}
+
+/// Distinguishes orders that are immediately actionable against the live
+/// book from ones parked in the trigger book until a price condition fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    Limit,
+    Market,
+    Stop,
+    StopLimit,
+}
+
+/// Why a resting or taker order left the book without becoming a fill.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutReason {
+    /// The order's remaining size was fully matched.
+    Filled,
+    /// A market order hit the end of the book before it could be fully matched.
+    NoLiquidity,
+    /// Cancelled by self-trade prevention rather than matched.
+    SelfTrade,
+}
+
+#[derive(Debug, Clone)]
+pub struct FillEvent {
+    pub maker_id: u64,
+    pub taker_id: u64,
+    pub price: Decimal,
+    pub size: f64,
+    pub maker_side: BidOrAsk,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutEvent {
+    pub order_id: u64,
+    pub reason: OutReason,
+}
+
+/// A structured record of something that happened during a matching pass,
+/// emitted instead of having callers reconstruct state from `Execution` pairs.
+#[derive(Debug, Clone)]
+pub enum BookEvent {
+    Fill(FillEvent),
+    Out(OutEvent),
+}
+
+/// How much of an order placement matched immediately versus rested on the book.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderSummary {
+    pub posted_order_id: Option<u64>,
+    pub total_filled: f64,
+    pub remaining: f64,
+}
+
+/// A volume-weighted quote for a hypothetical fill against current book
+/// depth, produced without mutating the book.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillQuote {
+    pub filled_size: f64,
+    pub avg_price: Option<Decimal>,
+    pub worst_price: Option<Decimal>,
+    pub levels_consumed: usize,
+    pub unfilled_size: f64,
+}
+
+/// One price level in a `depth` snapshot, with its volume aggregated across
+/// every resting order at that price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthLevel {
+    pub price: Decimal,
+    pub total_volume: f64,
+}
+
 #[derive(Debug)]
 pub struct OrderBook {
-    asks: HashMap<Decimal, Limit>,
-    bids: HashMap<Decimal, Limit>,
+    asks: BTreeMap<Decimal, Limit>,
+    bids: BTreeMap<Decimal, Limit>,
+    // order id -> (side, price level) so cancel/amend can jump straight to the
+    // right Limit instead of scanning the whole book.
+    order_index: HashMap<u64, (BidOrAsk, Decimal)>,
+    events: VecDeque<BookEvent>,
+    // Resting stop/stop-limit orders, indexed by trigger price, kept separate
+    // from the live `bids`/`asks` book until activated.
+    stop_bids: BTreeMap<Decimal, Limit>,
+    stop_asks: BTreeMap<Decimal, Limit>,
+    last_trade_price: Option<Decimal>,
 }
 
 impl OrderBook {
     pub fn new() -> OrderBook {
         OrderBook {
-            asks: HashMap::new(),
-            bids: HashMap::new(),
+            asks: BTreeMap::new(),
+            bids: BTreeMap::new(),
+            order_index: HashMap::new(),
+            events: VecDeque::new(),
+            stop_bids: BTreeMap::new(),
+            stop_asks: BTreeMap::new(),
+            last_trade_price: None,
+        }
+    }
+
+    /// Drains and returns every `BookEvent` emitted since the last drain.
+    pub fn drain_events(&mut self) -> Vec<BookEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn fill_market_order(
+        &mut self,
+        market_order: &mut Order,
+        stp: SelfTradeBehavior,
+    ) -> Vec<Execution> {
+        let executions = self.fill_order(market_order, None, stp);
+
+        if !market_order.is_filled() {
+            self.events.push_back(BookEvent::Out(OutEvent {
+                order_id: market_order.id,
+                reason: OutReason::NoLiquidity,
+            }));
         }
+
+        executions
     }
 
-    pub fn fill_market_order(&mut self, market_order: &mut Order) -> Vec<Execution> {
-        self.fill_order(market_order, None)
+    pub fn fill_limit_order(
+        &mut self,
+        limit_order: &mut Order,
+        price: Decimal,
+        stp: SelfTradeBehavior,
+    ) -> Vec<Execution> {
+        self.fill_order(limit_order, Some(price), stp)
     }
 
-    pub fn fill_limit_order(&mut self, limit_order: &mut Order, price: Decimal) -> Vec<Execution> {
-        self.fill_order(limit_order, Some(price))
+    /// Matches `order` against the opposite side of the book, then rests
+    /// whatever size is left over at `price`. Returns a summary of how much
+    /// matched immediately versus how much now sits on the book.
+    pub fn place_limit_order(
+        &mut self,
+        price: Decimal,
+        mut order: Order,
+        stp: SelfTradeBehavior,
+    ) -> OrderSummary {
+        let order_id = order.id;
+
+        let executions = self.fill_order(&mut order, Some(price), stp);
+
+        let remaining = order.size;
+        // Count only what actually traded: size dropped by self-trade
+        // prevention leaves `order.size` without producing an execution.
+        let total_filled: f64 = executions
+            .iter()
+            .filter(|execution| execution.id == order_id)
+            .map(|execution| execution.size)
+            .sum();
+
+        let posted_order_id = if remaining > 0.0 {
+            self.add_limit_order(price, order);
+            Some(order_id)
+        } else {
+            None
+        };
+
+        OrderSummary {
+            posted_order_id,
+            total_filled,
+            remaining,
+        }
     }
 
     pub fn fill_order(
         &mut self,
         order: &mut Order,
         limit_price: Option<Decimal>,
+        stp: SelfTradeBehavior,
+    ) -> Vec<Execution> {
+        let executions = self.match_order(order, limit_price, stp);
+        self.activate_triggered_stops(stp);
+        executions
+    }
+
+    // The core matching loop, shared by `fill_order` and stop activation.
+    // Kept separate from `fill_order` so activating a cascade of triggered
+    // stops doesn't re-enter the stop-activation step for every fill inside
+    // the cascade.
+    fn match_order(
+        &mut self,
+        order: &mut Order,
+        limit_price: Option<Decimal>,
+        stp: SelfTradeBehavior,
     ) -> Vec<Execution> {
         let mut executions = Vec::new();
+        let mut emptied_prices = Vec::new();
         // Vec = >matches
         let limits = match order.bid_or_ask {
-            BidOrAsk::Bid => self.ask_limits(limit_price),
-            BidOrAsk::Ask => self.bid_limits(limit_price),
+            BidOrAsk::Bid => Self::matching_levels(&mut self.asks, limit_price, true),
+            BidOrAsk::Ask => Self::matching_levels(&mut self.bids, limit_price, false),
         };
 
         for limit_order in limits {
-            let execs = limit_order.fill_order(order);
+            let execs = limit_order.fill_order(order, &mut self.events, &mut self.order_index, stp);
+            if !execs.is_empty() {
+                self.last_trade_price = Some(limit_order.price);
+            }
             executions.extend(execs);
 
+            if limit_order.orders.is_empty() {
+                emptied_prices.push(limit_order.price);
+            }
+
             if order.is_filled() {
                 break;
             }
         }
 
+        // `limits` borrows `self.asks`/`self.bids` mutably, so the now-empty
+        // levels it walked can only be pruned from the map once that borrow
+        // has ended — hence the two-pass collect-then-remove here rather
+        // than removing them inline above.
+        if !emptied_prices.is_empty() {
+            let levels = match order.bid_or_ask {
+                BidOrAsk::Bid => &mut self.asks,
+                BidOrAsk::Ask => &mut self.bids,
+            };
+            for price in emptied_prices {
+                levels.remove(&price);
+            }
+        }
+
         executions
     }
 
-    // BID (BUY Order) => ASKS => sorted cheapest price
-    fn ask_limits(&mut self, limit_price: Option<Decimal>) -> Vec<&mut Limit> {
-        let mut limits: Vec<&mut Limit> = match limit_price {
-            Some(limit_price) => self
-                .asks
-                .values_mut()
-                .filter(|limit| limit.price <= limit_price)
-                .collect::<Vec<&mut Limit>>(),
-            None => self.asks.values_mut().collect::<Vec<&mut Limit>>(),
+    /// Rests a stop or stop-limit order in the trigger book. It never touches
+    /// the live book directly — it only enters play once the last trade price
+    /// crosses its `trigger_price`, via `activate_triggered_stops`. Rejects an
+    /// order whose `order_type` isn't `Stop`/`StopLimit`, or one missing a
+    /// `trigger_price`, instead of panicking later during activation.
+    pub fn place_stop_order(&mut self, order: Order) -> Result<(), String> {
+        match order.order_type {
+            OrderType::Stop | OrderType::StopLimit => {}
+            OrderType::Limit | OrderType::Market => {
+                return Err(format!(
+                    "order {} has type {:?}, which cannot be placed in the trigger book",
+                    order.id, order.order_type
+                ))
+            }
+        }
+
+        let trigger_price = order
+            .trigger_price
+            .ok_or_else(|| format!("stop order {} has no trigger price", order.id))?;
+
+        let levels = match order.bid_or_ask {
+            BidOrAsk::Bid => &mut self.stop_bids,
+            BidOrAsk::Ask => &mut self.stop_asks,
         };
-        limits.sort_by(|a, b| a.price.cmp(&b.price));
-        limits
+
+        levels
+            .entry(trigger_price)
+            .or_insert_with(|| Limit::new(trigger_price))
+            .add_order(order);
+
+        Ok(())
+    }
+
+    // Scans resting stop/stop-limit orders against the most recent trade
+    // price and activates any whose trigger has been reached: buy-stops at
+    // or below the current price, sell-stops at or above it. Loops until a
+    // pass activates nothing, so a chain of stops triggering each other
+    // cascades fully in one call instead of needing the caller to re-invoke
+    // it. `MAX_CASCADE_PASSES` bounds that loop in case activated orders keep
+    // re-arming triggers indefinitely.
+    fn activate_triggered_stops(&mut self, stp: SelfTradeBehavior) {
+        const MAX_CASCADE_PASSES: usize = 1_000;
+
+        for _ in 0..MAX_CASCADE_PASSES {
+            let last_trade_price = match self.last_trade_price {
+                Some(price) => price,
+                None => break,
+            };
+
+            let mut triggered = Vec::new();
+
+            let buy_stop_prices: Vec<Decimal> = self
+                .stop_bids
+                .range(..=last_trade_price)
+                .map(|(price, _)| *price)
+                .collect();
+            for price in buy_stop_prices {
+                if let Some(limit) = self.stop_bids.remove(&price) {
+                    triggered.extend(limit.orders);
+                }
+            }
+
+            let sell_stop_prices: Vec<Decimal> = self
+                .stop_asks
+                .range(last_trade_price..)
+                .map(|(price, _)| *price)
+                .collect();
+            for price in sell_stop_prices {
+                if let Some(limit) = self.stop_asks.remove(&price) {
+                    triggered.extend(limit.orders);
+                }
+            }
+
+            if triggered.is_empty() {
+                break;
+            }
+
+            for order in triggered {
+                self.activate_stop(order, stp);
+            }
+        }
     }
 
-    // ASK (SELL Order) => BIDS => sorted highest price
+    // Converts a triggered stop order into a market order (`OrderType::Stop`)
+    // or a resting limit order at its trigger price (`OrderType::StopLimit`)
+    // and runs it through the live book.
+    fn activate_stop(&mut self, mut order: Order, stp: SelfTradeBehavior) {
+        match order.order_type {
+            OrderType::Stop => {
+                self.match_order(&mut order, None, stp);
+                if !order.is_filled() {
+                    self.events.push_back(BookEvent::Out(OutEvent {
+                        order_id: order.id,
+                        reason: OutReason::NoLiquidity,
+                    }));
+                }
+            }
+            OrderType::StopLimit => {
+                let price = order
+                    .trigger_price
+                    .expect("stop-limit order must have a trigger price");
+                self.match_order(&mut order, Some(price), stp);
+                if !order.is_filled() {
+                    self.add_limit_order(price, order);
+                }
+            }
+            OrderType::Limit | OrderType::Market => {
+                unreachable!("only stop orders are held in the trigger book")
+            }
+        }
+    }
+
+    // Walks `levels` in price priority order, stopping once a level is no
+    // longer marketable. Takes the BTreeMap field directly (rather than
+    // `&mut self`) so callers can still touch other `OrderBook` fields, such
+    // as the event queue, while the borrow is alive.
+    fn matching_levels(
+        levels: &mut BTreeMap<Decimal, Limit>,
+        limit_price: Option<Decimal>,
+        ascending: bool,
+    ) -> Vec<&mut Limit> {
+        if ascending {
+            match limit_price {
+                Some(limit_price) => levels
+                    .range_mut(..=limit_price)
+                    .map(|(_, limit)| limit)
+                    .collect(),
+                None => levels.values_mut().collect(),
+            }
+        } else {
+            match limit_price {
+                Some(limit_price) => levels
+                    .range_mut(limit_price..)
+                    .rev()
+                    .map(|(_, limit)| limit)
+                    .collect(),
+                None => levels.iter_mut().rev().map(|(_, limit)| limit).collect(),
+            }
+        }
+    }
+
+    // BID (BUY Order) => ASKS => cheapest price first, stopping once a level
+    // is no longer marketable. The BTreeMap is kept in price order already,
+    // so this is a plain in-order walk rather than a filter-then-sort.
+    fn ask_limits(&mut self, limit_price: Option<Decimal>) -> Vec<&mut Limit> {
+        Self::matching_levels(&mut self.asks, limit_price, true)
+    }
+
+    // ASK (SELL Order) => BIDS => highest price first, stopping once a level
+    // is no longer marketable.
     fn bid_limits(&mut self, limit_price: Option<Decimal>) -> Vec<&mut Limit> {
-        let mut limits = match limit_price {
-            Some(limit_price) => self
+        Self::matching_levels(&mut self.bids, limit_price, false)
+    }
+
+    /// The lowest resting ask price, if any.
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.asks.keys().next().copied()
+    }
+
+    /// The highest resting bid price, if any.
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.bids.keys().next_back().copied()
+    }
+
+    /// The gap between the best ask and best bid, or `None` if either side
+    /// of the book is empty.
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()? - self.best_bid()?)
+    }
+
+    /// The top `n` resting price levels on `side`, best price first, with
+    /// volume aggregated across every order resting at that price.
+    pub fn depth(&self, side: BidOrAsk, n: usize) -> Vec<DepthLevel> {
+        match side {
+            BidOrAsk::Bid => self
                 .bids
-                .values_mut()
-                .filter(|limit| limit.price >= limit_price)
-                .collect::<Vec<&mut Limit>>(),
-            None => self.bids.values_mut().collect::<Vec<&mut Limit>>(),
+                .iter()
+                .rev()
+                .take(n)
+                .map(|(price, limit)| DepthLevel {
+                    price: *price,
+                    total_volume: limit.total_volume(),
+                })
+                .collect(),
+            BidOrAsk::Ask => self
+                .asks
+                .iter()
+                .take(n)
+                .map(|(price, limit)| DepthLevel {
+                    price: *price,
+                    total_volume: limit.total_volume(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Walks the opposite side of the book exactly like `fill_order` but
+    /// mutates nothing, returning a volume-weighted quote for a hypothetical
+    /// order of `size` on `side`. Useful for pre-trade checks and for
+    /// valuing a quantity against current depth without touching book state.
+    pub fn simulate_fill(
+        &self,
+        side: BidOrAsk,
+        size: f64,
+        limit_price: Option<Decimal>,
+    ) -> FillQuote {
+        let levels: Vec<(&Decimal, &Limit)> = match side {
+            BidOrAsk::Bid => match limit_price {
+                Some(limit_price) => self.asks.range(..=limit_price).collect(),
+                None => self.asks.iter().collect(),
+            },
+            BidOrAsk::Ask => match limit_price {
+                Some(limit_price) => self.bids.range(limit_price..).rev().collect(),
+                None => self.bids.iter().rev().collect(),
+            },
         };
-        limits.sort_by(|a, b| b.price.cmp(&a.price));
-        limits
+
+        let mut remaining = size;
+        let mut filled_size = 0.0;
+        let mut notional = Decimal::ZERO;
+        let mut worst_price = None;
+        let mut levels_consumed = 0;
+
+        for (price, limit) in levels {
+            if remaining <= 0.0 {
+                break;
+            }
+
+            let available = limit.total_volume();
+            if available <= 0.0 {
+                continue;
+            }
+
+            let matched = remaining.min(available);
+            filled_size += matched;
+            notional += Decimal::from_f64(matched).unwrap_or(Decimal::ZERO) * price;
+            worst_price = Some(*price);
+            levels_consumed += 1;
+            remaining -= matched;
+        }
+
+        let avg_price = Decimal::from_f64(filled_size)
+            .filter(|f| !f.is_zero())
+            .map(|f| notional / f);
+
+        FillQuote {
+            filled_size,
+            avg_price,
+            worst_price,
+            levels_consumed,
+            unfilled_size: remaining.max(0.0),
+        }
     }
 
     pub fn add_limit_order(&mut self, price: Decimal, order: Order) {
-        match order.bid_or_ask {
+        let bid_or_ask = order.bid_or_ask.clone();
+        let order_id = order.id;
+
+        match bid_or_ask {
             BidOrAsk::Bid => match self.bids.get_mut(&price) {
                 Some(limit) => limit.add_order(order),
                 None => {
@@ -102,6 +525,57 @@ impl OrderBook {
                 }
             },
         }
+
+        self.order_index.insert(order_id, (bid_or_ask, price));
+    }
+
+    /// Removes a resting order from the book, dropping its price level once
+    /// the level is left empty. Returns `None` if the order is unknown (e.g.
+    /// it was already filled or cancelled).
+    pub fn cancel_order(&mut self, order_id: u64) -> Option<Order> {
+        let (bid_or_ask, price) = self.order_index.remove(&order_id)?;
+        let limits = match bid_or_ask {
+            BidOrAsk::Bid => &mut self.bids,
+            BidOrAsk::Ask => &mut self.asks,
+        };
+
+        let limit = limits.get_mut(&price)?;
+        let removed = limit.remove_order(order_id);
+
+        if limit.orders.is_empty() {
+            limits.remove(&price);
+        }
+
+        removed
+    }
+
+    /// Reduces a resting order's size in place. Rejects a `new_size` larger
+    /// than the order's current size — amends are reduce-only, matching real
+    /// order books.
+    pub fn amend_order(&mut self, order_id: u64, new_size: f64) -> Result<(), String> {
+        if new_size <= 0.0 {
+            return Err(format!(
+                "amend rejected: new size {} must be positive; use cancel_order to remove an order",
+                new_size
+            ));
+        }
+
+        let (bid_or_ask, price) = self
+            .order_index
+            .get(&order_id)
+            .cloned()
+            .ok_or_else(|| format!("order {} not found", order_id))?;
+
+        let limits = match bid_or_ask {
+            BidOrAsk::Bid => &mut self.bids,
+            BidOrAsk::Ask => &mut self.asks,
+        };
+
+        let limit = limits
+            .get_mut(&price)
+            .ok_or_else(|| format!("order {} not found", order_id))?;
+
+        limit.amend_order(order_id, new_size)
     }
 }
 
@@ -123,11 +597,69 @@ impl Limit {
         self.orders.iter().map(|order| order.size).sum()
     }
 
-    fn fill_order(&mut self, market_order: &mut Order) -> Vec<Execution> {
+    fn fill_order(
+        &mut self,
+        market_order: &mut Order,
+        events: &mut VecDeque<BookEvent>,
+        order_index: &mut HashMap<u64, (BidOrAsk, Decimal)>,
+        stp: SelfTradeBehavior,
+    ) -> Vec<Execution> {
         let mut executions = Vec::new();
         while !market_order.is_filled() && !self.orders.is_empty() {
             let mut limit_order = self.orders.front_mut().unwrap();
 
+            if limit_order.owner_id == market_order.owner_id {
+                match stp {
+                    SelfTradeBehavior::CancelTake => {
+                        events.push_back(BookEvent::Out(OutEvent {
+                            order_id: market_order.id,
+                            reason: OutReason::SelfTrade,
+                        }));
+                        market_order.size = 0.0;
+                        break;
+                    }
+                    SelfTradeBehavior::CancelProvide => {
+                        events.push_back(BookEvent::Out(OutEvent {
+                            order_id: limit_order.id,
+                            reason: OutReason::SelfTrade,
+                        }));
+                        order_index.remove(&limit_order.id);
+                        self.orders.pop_front();
+                        continue;
+                    }
+                    SelfTradeBehavior::DecrementAndCancel => {
+                        if market_order.size <= limit_order.size {
+                            limit_order.size -= market_order.size;
+                            events.push_back(BookEvent::Out(OutEvent {
+                                order_id: market_order.id,
+                                reason: OutReason::SelfTrade,
+                            }));
+                            market_order.size = 0.0;
+
+                            if limit_order.is_filled() {
+                                events.push_back(BookEvent::Out(OutEvent {
+                                    order_id: limit_order.id,
+                                    reason: OutReason::SelfTrade,
+                                }));
+                                order_index.remove(&limit_order.id);
+                                self.orders.pop_front();
+                            }
+                            break;
+                        } else {
+                            market_order.size -= limit_order.size;
+                            events.push_back(BookEvent::Out(OutEvent {
+                                order_id: limit_order.id,
+                                reason: OutReason::SelfTrade,
+                            }));
+                            limit_order.size = 0.0;
+                            order_index.remove(&limit_order.id);
+                            self.orders.pop_front();
+                            continue;
+                        }
+                    }
+                }
+            }
+
             let shares = if market_order.size >= limit_order.size {
                 limit_order.size
             } else {
@@ -136,10 +668,23 @@ impl Limit {
             executions.push(Execution::new(market_order, shares, self.price));
             executions.push(Execution::new(limit_order, shares, self.price));
 
+            events.push_back(BookEvent::Fill(FillEvent {
+                maker_id: limit_order.id,
+                taker_id: market_order.id,
+                price: self.price,
+                size: shares,
+                maker_side: limit_order.bid_or_ask.clone(),
+            }));
+
             market_order.size -= shares;
             limit_order.size -= shares;
 
             if limit_order.is_filled() {
+                events.push_back(BookEvent::Out(OutEvent {
+                    order_id: limit_order.id,
+                    reason: OutReason::Filled,
+                }));
+                order_index.remove(&limit_order.id);
                 self.orders.pop_front();
             }
         }
@@ -150,6 +695,47 @@ impl Limit {
     fn add_order(&mut self, order: Order) {
         self.orders.push_back(order);
     }
+
+    fn remove_order(&mut self, order_id: u64) -> Option<Order> {
+        let mut removed = None;
+        let mut remaining = LinkedList::new();
+
+        while let Some(order) = self.orders.pop_front() {
+            if removed.is_none() && order.id == order_id {
+                removed = Some(order);
+            } else {
+                remaining.push_back(order);
+            }
+        }
+
+        self.orders = remaining;
+        removed
+    }
+
+    fn amend_order(&mut self, order_id: u64, new_size: f64) -> Result<(), String> {
+        let order = self
+            .orders
+            .iter_mut()
+            .find(|order| order.id == order_id)
+            .ok_or_else(|| format!("order {} not found", order_id))?;
+
+        if new_size > order.size {
+            return Err(format!(
+                "amend rejected: new size {} exceeds existing size {}",
+                new_size, order.size
+            ));
+        }
+
+        if new_size <= 0.0 {
+            return Err(format!(
+                "amend rejected: new size {} must be positive; use cancel_order to remove an order",
+                new_size
+            ));
+        }
+
+        order.size = new_size;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -157,20 +743,62 @@ pub struct Order {
     id: u64,
     size: f64,
     bid_or_ask: BidOrAsk,
+    owner_id: u64,
+    order_type: OrderType,
+    trigger_price: Option<Decimal>,
 }
 
 impl Order {
-    pub fn new(id: u64, bid_or_ask: BidOrAsk, size: f64) -> Order {
+    pub fn new(id: u64, bid_or_ask: BidOrAsk, size: f64, owner_id: u64) -> Order {
         Order {
             id,
             bid_or_ask,
             size,
+            owner_id,
+            order_type: OrderType::Limit,
+            trigger_price: None,
+        }
+    }
+
+    /// Builds a resting stop or stop-limit order. It only enters the live
+    /// book once `trigger_price` is crossed by the last trade price.
+    pub fn new_stop(
+        id: u64,
+        bid_or_ask: BidOrAsk,
+        size: f64,
+        owner_id: u64,
+        order_type: OrderType,
+        trigger_price: Decimal,
+    ) -> Order {
+        Order {
+            id,
+            bid_or_ask,
+            size,
+            owner_id,
+            order_type,
+            trigger_price: Some(trigger_price),
         }
     }
 
     pub fn is_filled(&self) -> bool {
         self.size == 0.0
     }
+
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    pub fn owner_id(&self) -> u64 {
+        self.owner_id
+    }
+
+    pub fn order_type(&self) -> OrderType {
+        self.order_type
+    }
+
+    pub fn trigger_price(&self) -> Option<Decimal> {
+        self.trigger_price
+    }
 }
 
 #[derive(Debug)]
@@ -201,21 +829,22 @@ pub mod tests {
     #[test]
     fn orderbook_fill_market_order_ask() {
         let mut orderbook = OrderBook::new();
-        orderbook.add_limit_order(dec!(500), Order::new(1, BidOrAsk::Ask, 10.0));
-        orderbook.add_limit_order(dec!(100), Order::new(2, BidOrAsk::Ask, 10.0));
-        orderbook.add_limit_order(dec!(200), Order::new(3, BidOrAsk::Ask, 10.0));
-        orderbook.add_limit_order(dec!(300), Order::new(4, BidOrAsk::Ask, 10.0));
+        orderbook.add_limit_order(dec!(500), Order::new(1, BidOrAsk::Ask, 10.0, 1));
+        orderbook.add_limit_order(dec!(100), Order::new(2, BidOrAsk::Ask, 10.0, 2));
+        orderbook.add_limit_order(dec!(200), Order::new(3, BidOrAsk::Ask, 10.0, 3));
+        orderbook.add_limit_order(dec!(300), Order::new(4, BidOrAsk::Ask, 10.0, 4));
 
-        let mut market_order = Order::new(5, BidOrAsk::Bid, 10.0);
-        let executions = orderbook.fill_market_order(&mut market_order);
+        let mut market_order = Order::new(5, BidOrAsk::Bid, 10.0, 5);
+        let executions = orderbook.fill_market_order(&mut market_order, SelfTradeBehavior::CancelProvide);
         println!("{:?}", executions);
 
-        let ask_limits = orderbook.ask_limits(None);
-        let matched_limit = ask_limits.get(0).unwrap();
-        assert_eq!(matched_limit.price, dec!(100));
         assert!(market_order.is_filled());
 
-        assert!(matched_limit.orders.is_empty());
+        // The level at 100 was fully swept, so it's pruned from the book
+        // rather than left behind empty — 200 is now the best ask.
+        let ask_limits = orderbook.ask_limits(None);
+        let matched_limit = ask_limits.get(0).unwrap();
+        assert_eq!(matched_limit.price, dec!(200));
 
         println!("{:?}", orderbook.ask_limits(None));
     }
@@ -223,26 +852,180 @@ pub mod tests {
     #[test]
     fn orderbook_fill_limit_order_ask() {
         let mut orderbook = OrderBook::new();
-        orderbook.add_limit_order(dec!(500), Order::new(1, BidOrAsk::Ask, 10.0));
-        orderbook.add_limit_order(dec!(100), Order::new(2, BidOrAsk::Ask, 10.0));
-        orderbook.add_limit_order(dec!(200), Order::new(3, BidOrAsk::Ask, 10.0));
-        orderbook.add_limit_order(dec!(300), Order::new(4, BidOrAsk::Ask, 10.0));
+        orderbook.add_limit_order(dec!(500), Order::new(1, BidOrAsk::Ask, 10.0, 1));
+        orderbook.add_limit_order(dec!(100), Order::new(2, BidOrAsk::Ask, 10.0, 2));
+        orderbook.add_limit_order(dec!(200), Order::new(3, BidOrAsk::Ask, 10.0, 3));
+        orderbook.add_limit_order(dec!(300), Order::new(4, BidOrAsk::Ask, 10.0, 4));
 
-        let mut limit_order = Order::new(5, BidOrAsk::Bid, 30.0);
-        let executions = orderbook.fill_limit_order(&mut limit_order, dec!(210));
+        let mut limit_order = Order::new(5, BidOrAsk::Bid, 30.0, 5);
+        let executions = orderbook.fill_limit_order(&mut limit_order, dec!(210), SelfTradeBehavior::CancelProvide);
         println!("{:?}", executions);
 
         assert_eq!(limit_order.size, 10.0);
         println!("{:?}", orderbook.ask_limits(None));
     }
 
+    #[test]
+    fn orderbook_fill_market_order_emits_fill_and_out_events() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 10.0, 1));
+
+        let mut market_order = Order::new(2, BidOrAsk::Bid, 10.0, 2);
+        orderbook.fill_market_order(&mut market_order, SelfTradeBehavior::CancelProvide);
+
+        let events = orderbook.drain_events();
+        assert_eq!(events.len(), 2);
+
+        match &events[0] {
+            BookEvent::Fill(fill) => {
+                assert_eq!(fill.maker_id, 1);
+                assert_eq!(fill.taker_id, 2);
+                assert_eq!(fill.size, 10.0);
+                assert_eq!(fill.price, dec!(100));
+            }
+            other => panic!("expected a fill event, got {:?}", other),
+        }
+
+        match &events[1] {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 1);
+                assert_eq!(out.reason, OutReason::Filled);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+
+        assert!(orderbook.drain_events().is_empty());
+    }
+
+    #[test]
+    fn orderbook_fill_market_order_emits_no_liquidity_out_event() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 4.0, 1));
+
+        let mut market_order = Order::new(2, BidOrAsk::Bid, 10.0, 2);
+        orderbook.fill_market_order(&mut market_order, SelfTradeBehavior::CancelProvide);
+
+        let events = orderbook.drain_events();
+        let out_event = events
+            .iter()
+            .find_map(|event| match event {
+                BookEvent::Out(out) if out.order_id == 2 => Some(out),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(out_event.reason, OutReason::NoLiquidity);
+    }
+
+    #[test]
+    fn orderbook_place_limit_order_rests_unfilled_remainder() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 4.0, 1));
+
+        let summary = orderbook.place_limit_order(dec!(100), Order::new(2, BidOrAsk::Bid, 10.0, 2), SelfTradeBehavior::CancelProvide);
+
+        assert_eq!(summary.total_filled, 4.0);
+        assert_eq!(summary.remaining, 6.0);
+        assert_eq!(summary.posted_order_id, Some(2));
+        assert_eq!(orderbook.best_bid(), Some(dec!(100)));
+    }
+
+    #[test]
+    fn orderbook_place_limit_order_fully_filled_does_not_rest() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 10.0, 1));
+
+        let summary = orderbook.place_limit_order(dec!(100), Order::new(2, BidOrAsk::Bid, 10.0, 2), SelfTradeBehavior::CancelProvide);
+
+        assert_eq!(summary.total_filled, 10.0);
+        assert_eq!(summary.remaining, 0.0);
+        assert_eq!(summary.posted_order_id, None);
+        assert_eq!(orderbook.best_bid(), None);
+    }
+
+    #[test]
+    fn orderbook_best_bid_and_ask() {
+        let mut orderbook = OrderBook::new();
+        assert_eq!(orderbook.best_ask(), None);
+        assert_eq!(orderbook.best_bid(), None);
+
+        orderbook.add_limit_order(dec!(500), Order::new(1, BidOrAsk::Ask, 10.0, 1));
+        orderbook.add_limit_order(dec!(100), Order::new(2, BidOrAsk::Ask, 10.0, 2));
+        orderbook.add_limit_order(dec!(90), Order::new(3, BidOrAsk::Bid, 10.0, 3));
+        orderbook.add_limit_order(dec!(95), Order::new(4, BidOrAsk::Bid, 10.0, 4));
+
+        assert_eq!(orderbook.best_ask(), Some(dec!(100)));
+        assert_eq!(orderbook.best_bid(), Some(dec!(95)));
+    }
+
+    #[test]
+    fn orderbook_cancel_order_removes_empty_limit() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        let cancelled = orderbook.cancel_order(1).unwrap();
+        assert_eq!(cancelled.size, 10.0);
+
+        assert!(orderbook.bid_limits(None).is_empty());
+        assert!(orderbook.cancel_order(1).is_none());
+    }
+
+    #[test]
+    fn orderbook_cancel_order_leaves_other_orders_at_level() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Bid, 10.0, 1));
+        orderbook.add_limit_order(dec!(100), Order::new(2, BidOrAsk::Bid, 5.0, 2));
+
+        orderbook.cancel_order(1).unwrap();
+
+        let bid_limits = orderbook.bid_limits(None);
+        let level = bid_limits.get(0).unwrap();
+        assert_eq!(level.orders.len(), 1);
+        assert_eq!(level.orders.front().unwrap().id, 2);
+    }
+
+    #[test]
+    fn orderbook_amend_order_reduces_size() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        orderbook.amend_order(1, 4.0).unwrap();
+
+        let bid_limits = orderbook.bid_limits(None);
+        let level = bid_limits.get(0).unwrap();
+        assert_eq!(level.orders.front().unwrap().size, 4.0);
+    }
+
+    #[test]
+    fn orderbook_amend_order_rejects_increase() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        assert!(orderbook.amend_order(1, 20.0).is_err());
+
+        let bid_limits = orderbook.bid_limits(None);
+        assert_eq!(bid_limits.get(0).unwrap().orders.front().unwrap().size, 10.0);
+    }
+
+    #[test]
+    fn orderbook_amend_order_rejects_a_non_positive_size() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        assert!(orderbook.amend_order(1, 0.0).is_err());
+        assert!(orderbook.amend_order(1, -1.0).is_err());
+
+        // Rejected, not a ghost zero-size order left resting.
+        let bid_limits = orderbook.bid_limits(None);
+        assert_eq!(bid_limits.get(0).unwrap().orders.front().unwrap().size, 10.0);
+    }
+
     #[test]
     fn limit_total_volume() {
         let price = dec!(10_000.0);
         let mut limit = Limit::new(price);
 
-        let buy_limit_order_a = Order::new(1, BidOrAsk::Bid, 100.0);
-        let buy_limit_order_b = Order::new(2, BidOrAsk::Bid, 100.0);
+        let buy_limit_order_a = Order::new(1, BidOrAsk::Bid, 100.0, 1);
+        let buy_limit_order_b = Order::new(2, BidOrAsk::Bid, 100.0, 2);
         limit.add_order(buy_limit_order_a);
         limit.add_order(buy_limit_order_b);
 
@@ -256,13 +1039,18 @@ pub mod tests {
         let price = dec!(10_000.0);
         let mut limit = Limit::new(price);
 
-        let buy_limit_order_a = Order::new(1, BidOrAsk::Bid, 100.0);
-        let buy_limit_order_b = Order::new(2, BidOrAsk::Bid, 100.0);
+        let buy_limit_order_a = Order::new(1, BidOrAsk::Bid, 100.0, 1);
+        let buy_limit_order_b = Order::new(2, BidOrAsk::Bid, 100.0, 2);
         limit.add_order(buy_limit_order_a);
         limit.add_order(buy_limit_order_b);
 
-        let mut market_sell_order = Order::new(3, BidOrAsk::Ask, 199.0);
-        limit.fill_order(&mut market_sell_order);
+        let mut market_sell_order = Order::new(3, BidOrAsk::Ask, 199.0, 3);
+        limit.fill_order(
+            &mut market_sell_order,
+            &mut VecDeque::new(),
+            &mut HashMap::new(),
+            SelfTradeBehavior::CancelProvide,
+        );
 
         assert!(market_sell_order.is_filled());
         assert_eq!(limit.orders.front().unwrap().size, 1.0);
@@ -275,15 +1063,318 @@ pub mod tests {
         let price = dec!(10_000.0);
         let mut limit = Limit::new(price);
 
-        let buy_limit_order = Order::new(1, BidOrAsk::Bid, 100.0);
+        let buy_limit_order = Order::new(1, BidOrAsk::Bid, 100.0, 1);
         limit.add_order(buy_limit_order);
 
-        let mut market_sell_order = Order::new(2, BidOrAsk::Ask, 99.0);
-        limit.fill_order(&mut market_sell_order);
+        let mut market_sell_order = Order::new(2, BidOrAsk::Ask, 99.0, 2);
+        limit.fill_order(
+            &mut market_sell_order,
+            &mut VecDeque::new(),
+            &mut HashMap::new(),
+            SelfTradeBehavior::CancelProvide,
+        );
 
         println!("{:?}", limit);
 
         assert!(market_sell_order.is_filled());
         assert_eq!(limit.orders.front().unwrap().size, 1.0);
     }
+
+    #[test]
+    fn limit_fill_order_cancel_take_aborts_taker_on_self_trade() {
+        let price = dec!(100);
+        let mut limit = Limit::new(price);
+        limit.add_order(Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        let mut taker = Order::new(2, BidOrAsk::Ask, 10.0, 1);
+        let mut events = VecDeque::new();
+        let executions = limit.fill_order(&mut taker, &mut events, &mut HashMap::new(), SelfTradeBehavior::CancelTake);
+
+        assert!(executions.is_empty());
+        assert!(taker.is_filled());
+        assert_eq!(limit.orders.front().unwrap().size, 10.0);
+
+        match events.pop_front().unwrap() {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 2);
+                assert_eq!(out.reason, OutReason::SelfTrade);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limit_fill_order_cancel_provide_skips_resting_order_and_keeps_matching() {
+        let price = dec!(100);
+        let mut limit = Limit::new(price);
+        limit.add_order(Order::new(1, BidOrAsk::Bid, 10.0, 1));
+        limit.add_order(Order::new(2, BidOrAsk::Bid, 10.0, 2));
+
+        let mut taker = Order::new(3, BidOrAsk::Ask, 10.0, 1);
+        let mut events = VecDeque::new();
+        let executions = limit.fill_order(&mut taker, &mut events, &mut HashMap::new(), SelfTradeBehavior::CancelProvide);
+
+        assert!(taker.is_filled());
+        assert_eq!(executions.len(), 2);
+        assert!(limit.orders.is_empty());
+
+        match events.pop_front().unwrap() {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 1);
+                assert_eq!(out.reason, OutReason::SelfTrade);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limit_fill_order_decrement_and_cancel_shrinks_the_larger_order() {
+        let price = dec!(100);
+        let mut limit = Limit::new(price);
+        limit.add_order(Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        let mut taker = Order::new(2, BidOrAsk::Ask, 4.0, 1);
+        let mut events = VecDeque::new();
+        let executions =
+            limit.fill_order(&mut taker, &mut events, &mut HashMap::new(), SelfTradeBehavior::DecrementAndCancel);
+
+        assert!(executions.is_empty());
+        assert!(taker.is_filled());
+        assert_eq!(limit.orders.front().unwrap().size, 6.0);
+
+        match events.pop_front().unwrap() {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 2);
+                assert_eq!(out.reason, OutReason::SelfTrade);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limit_fill_order_decrement_and_cancel_emits_an_out_event_for_an_equal_size_self_trade() {
+        let price = dec!(100);
+        let mut limit = Limit::new(price);
+        limit.add_order(Order::new(1, BidOrAsk::Bid, 10.0, 1));
+
+        let mut taker = Order::new(2, BidOrAsk::Ask, 10.0, 1);
+        let mut events = VecDeque::new();
+        let executions =
+            limit.fill_order(&mut taker, &mut events, &mut HashMap::new(), SelfTradeBehavior::DecrementAndCancel);
+
+        assert!(executions.is_empty());
+        assert!(taker.is_filled());
+        assert!(limit.orders.is_empty());
+
+        // Both sides vanished from the book, so both need an Out event —
+        // not just the taker.
+        assert_eq!(events.len(), 2);
+        match events.pop_front().unwrap() {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 2);
+                assert_eq!(out.reason, OutReason::SelfTrade);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+        match events.pop_front().unwrap() {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 1);
+                assert_eq!(out.reason, OutReason::SelfTrade);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limit_fill_order_decrement_and_cancel_clears_smaller_resting_order() {
+        let price = dec!(100);
+        let mut limit = Limit::new(price);
+        limit.add_order(Order::new(1, BidOrAsk::Bid, 4.0, 1));
+        limit.add_order(Order::new(2, BidOrAsk::Bid, 10.0, 2));
+
+        let mut taker = Order::new(3, BidOrAsk::Ask, 10.0, 1);
+        let mut events = VecDeque::new();
+        let executions =
+            limit.fill_order(&mut taker, &mut events, &mut HashMap::new(), SelfTradeBehavior::DecrementAndCancel);
+
+        assert!(taker.is_filled());
+        assert_eq!(executions.len(), 2);
+        assert_eq!(limit.orders.front().unwrap().id, 2);
+
+        match events.pop_front().unwrap() {
+            BookEvent::Out(out) => {
+                assert_eq!(out.order_id, 1);
+                assert_eq!(out.reason, OutReason::SelfTrade);
+            }
+            other => panic!("expected an out event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stop_order_does_not_touch_the_live_book_until_triggered() {
+        let mut orderbook = OrderBook::new();
+        let stop_sell = Order::new_stop(1, BidOrAsk::Ask, 10.0, 1, OrderType::Stop, dec!(90));
+        orderbook.place_stop_order(stop_sell).unwrap();
+
+        assert_eq!(orderbook.best_ask(), None);
+        assert!(orderbook.ask_limits(None).is_empty());
+    }
+
+    #[test]
+    fn stop_order_activates_as_a_market_order_once_last_trade_price_is_reached() {
+        let mut orderbook = OrderBook::new();
+
+        let stop_sell = Order::new_stop(1, BidOrAsk::Ask, 10.0, 1, OrderType::Stop, dec!(90));
+        orderbook.place_stop_order(stop_sell).unwrap();
+
+        // A resting bid at 90 that the incoming sell will cross, setting the
+        // last trade price to 90 and triggering the stop above.
+        orderbook.add_limit_order(dec!(90), Order::new(2, BidOrAsk::Bid, 10.0, 2));
+
+        let mut taker = Order::new(3, BidOrAsk::Ask, 10.0, 3);
+        orderbook.fill_market_order(&mut taker, SelfTradeBehavior::CancelProvide);
+
+        // The bid that set the trade price was fully consumed by the taker,
+        // so the activated stop (itself a market sell) finds no liquidity.
+        let events = orderbook.drain_events();
+        let stop_had_no_liquidity = events.iter().any(|event| {
+            matches!(
+                event,
+                BookEvent::Out(out) if out.order_id == 1 && out.reason == OutReason::NoLiquidity
+            )
+        });
+        assert!(stop_had_no_liquidity, "activated stop order should have been dropped for lack of liquidity");
+    }
+
+    #[test]
+    fn stop_limit_order_rests_on_the_live_book_once_triggered() {
+        let mut orderbook = OrderBook::new();
+
+        let stop_limit_sell =
+            Order::new_stop(1, BidOrAsk::Ask, 10.0, 1, OrderType::StopLimit, dec!(90));
+        orderbook.place_stop_order(stop_limit_sell).unwrap();
+
+        // Crosses a resting bid at 90, moving the last trade price to 90 and
+        // triggering the sell stop-limit armed at the same level.
+        orderbook.add_limit_order(dec!(90), Order::new(2, BidOrAsk::Bid, 5.0, 2));
+
+        let mut taker = Order::new(3, BidOrAsk::Ask, 5.0, 3);
+        orderbook.fill_market_order(&mut taker, SelfTradeBehavior::CancelProvide);
+
+        assert_eq!(orderbook.best_ask(), Some(dec!(90)));
+        let ask_limits = orderbook.ask_limits(None);
+        assert_eq!(ask_limits.get(0).unwrap().orders.front().unwrap().size, 10.0);
+    }
+
+    #[test]
+    fn place_stop_order_rejects_a_plain_limit_order() {
+        let mut orderbook = OrderBook::new();
+        let order = Order::new(1, BidOrAsk::Ask, 10.0, 1);
+        assert!(orderbook.place_stop_order(order).is_err());
+    }
+
+    #[test]
+    fn place_stop_order_rejects_a_stop_order_tagged_with_the_wrong_order_type() {
+        let mut orderbook = OrderBook::new();
+        let order = Order::new_stop(1, BidOrAsk::Ask, 10.0, 1, OrderType::Limit, dec!(90));
+        assert!(orderbook.place_stop_order(order).is_err());
+    }
+
+    #[test]
+    fn simulate_fill_quotes_volume_weighted_average_price_across_levels() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+        orderbook.add_limit_order(dec!(101), Order::new(2, BidOrAsk::Ask, 5.0, 2));
+
+        let quote = orderbook.simulate_fill(BidOrAsk::Bid, 8.0, None);
+
+        assert_eq!(quote.filled_size, 8.0);
+        assert_eq!(quote.unfilled_size, 0.0);
+        assert_eq!(quote.worst_price, Some(dec!(101)));
+        assert_eq!(quote.levels_consumed, 2);
+        // 5 @ 100 + 3 @ 101 = 803, / 8 = 100.375
+        assert_eq!(quote.avg_price, Some(dec!(100.375)));
+
+        // A read-only simulation must not touch the book.
+        assert_eq!(orderbook.best_ask(), Some(dec!(100)));
+    }
+
+    #[test]
+    fn simulate_fill_reports_unfilled_size_when_depth_runs_out() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+
+        let quote = orderbook.simulate_fill(BidOrAsk::Bid, 10.0, None);
+
+        assert_eq!(quote.filled_size, 5.0);
+        assert_eq!(quote.unfilled_size, 5.0);
+        assert_eq!(quote.avg_price, Some(dec!(100)));
+    }
+
+    #[test]
+    fn simulate_fill_respects_a_limit_price() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+        orderbook.add_limit_order(dec!(200), Order::new(2, BidOrAsk::Ask, 5.0, 2));
+
+        let quote = orderbook.simulate_fill(BidOrAsk::Bid, 10.0, Some(dec!(100)));
+
+        assert_eq!(quote.filled_size, 5.0);
+        assert_eq!(quote.unfilled_size, 5.0);
+        assert_eq!(quote.worst_price, Some(dec!(100)));
+    }
+
+    #[test]
+    fn spread_is_the_gap_between_best_bid_and_best_ask() {
+        let mut orderbook = OrderBook::new();
+        assert_eq!(orderbook.spread(), None);
+
+        orderbook.add_limit_order(dec!(101), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+        orderbook.add_limit_order(dec!(99), Order::new(2, BidOrAsk::Bid, 5.0, 2));
+
+        assert_eq!(orderbook.spread(), Some(dec!(2)));
+    }
+
+    #[test]
+    fn depth_returns_top_n_levels_with_aggregated_volume() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(101), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+        orderbook.add_limit_order(dec!(101), Order::new(2, BidOrAsk::Ask, 5.0, 2));
+        orderbook.add_limit_order(dec!(102), Order::new(3, BidOrAsk::Ask, 1.0, 3));
+
+        let depth = orderbook.depth(BidOrAsk::Ask, 1);
+
+        assert_eq!(
+            depth,
+            vec![DepthLevel {
+                price: dec!(101),
+                total_volume: 10.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn fully_sweeping_a_level_drops_it_from_the_book() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+
+        let mut taker = Order::new(2, BidOrAsk::Bid, 5.0, 2);
+        orderbook.fill_market_order(&mut taker, SelfTradeBehavior::CancelProvide);
+
+        assert_eq!(orderbook.best_ask(), None);
+        assert!(orderbook.ask_limits(None).is_empty());
+        assert!(orderbook.depth(BidOrAsk::Ask, 10).is_empty());
+    }
+
+    #[test]
+    fn draining_a_level_via_self_trade_prevention_drops_it_from_the_book() {
+        let mut orderbook = OrderBook::new();
+        orderbook.add_limit_order(dec!(100), Order::new(1, BidOrAsk::Ask, 5.0, 1));
+
+        let mut taker = Order::new(2, BidOrAsk::Bid, 5.0, 1);
+        orderbook.fill_market_order(&mut taker, SelfTradeBehavior::CancelProvide);
+
+        assert_eq!(orderbook.best_ask(), None);
+        assert!(orderbook.depth(BidOrAsk::Ask, 10).is_empty());
+    }
 }